@@ -34,6 +34,7 @@
 //!
 //! License: CC0, attribution kindly requested. Blame taken too,
 //! but not liability.
+#![cfg_attr(not(feature = "std"), no_std)]
 
 const RHO: [u32; 24] = [
 	 1,  3,  6, 10, 15, 21,
@@ -91,15 +92,20 @@ macro_rules! FOR5 {
 	}
 }
 
-/// keccak-f[1600]
-pub fn keccakf(a: &mut [u64]) {
+/// keccak-f[1600], reduced to its last `rounds` rounds. A full, 24-round
+/// call starts Iota at `RC[0]`; a reduced-round call starts at the
+/// matching tail of `RC` so the constants line up the same way a real
+/// 24-round permutation run that far would leave them.
+pub fn keccak_p(a: &mut [u64], rounds: usize) {
+	assert!(rounds <= 24, "keccak_p supports at most 24 rounds");
+
 	unsafe {
 		let mut b: [u64; 5] = [0; 5];
 		let mut t: u64;
 		let mut x: usize;
 		let mut y: usize;
 
-		for i in 0..24 {
+		for i in (24 - rounds)..24 {
 			// Theta
 			FOR5!(x, 1, {
 				*b.get_unchecked_mut(x) = 0;
@@ -115,7 +121,7 @@ pub fn keccakf(a: &mut [u64]) {
 			});
 
 			// Rho and pi
-			t = *a.get_unchecked(1); 
+			t = *a.get_unchecked(1);
 			x = 0;
 			REPEAT24!({
 				*b.get_unchecked_mut(0) = *a.get_unchecked(*PI.get_unchecked(x));
@@ -141,6 +147,68 @@ pub fn keccakf(a: &mut [u64]) {
 	}
 }
 
+/// keccak-f[1600]
+pub fn keccakf(a: &mut [u64]) {
+	keccak_p(a, 24);
+}
+
+/// keccak-f[800], the 32-bit-lane variant used by Ethereum's ProgPoW,
+/// reduced to its last `rounds` rounds the same way `keccak_p` is. Per
+/// FIPS 202 Algorithm 7, Keccak-f[800]'s own full round count is
+/// `12 + 2*5 = 22` (not the 24 of Keccak-f[1600]), so a full, unreduced
+/// call starts Iota at `RC[0]` after 22 rounds, and `RC`'s 24 entries are
+/// truncated to their first 22.
+pub fn keccak_f800(a: &mut [u32; 25], rounds: usize) {
+	assert!(rounds <= 22, "keccak_f800 supports at most 22 rounds");
+
+	unsafe {
+		let mut b: [u32; 5] = [0; 5];
+		let mut t: u32;
+		let mut x: usize;
+		let mut y: usize;
+
+		for i in (22 - rounds)..22 {
+			// Theta
+			FOR5!(x, 1, {
+				*b.get_unchecked_mut(x) = 0;
+				FOR5!(y, 5, {
+					*b.get_unchecked_mut(x) ^= *a.get_unchecked(x + y);
+				});
+			});
+
+			FOR5!(x, 1, {
+				FOR5!(y, 5, {
+					*a.get_unchecked_mut(y + x) ^= *b.get_unchecked((x + 4) % 5) ^ b.get_unchecked((x + 1) % 5).rotate_left(1);
+				});
+			});
+
+			// Rho and pi
+			t = *a.get_unchecked(1);
+			x = 0;
+			REPEAT24!({
+				*b.get_unchecked_mut(0) = *a.get_unchecked(*PI.get_unchecked(x));
+				*a.get_unchecked_mut(*PI.get_unchecked(x)) = t.rotate_left(*RHO.get_unchecked(x) % 32);
+			}, {
+				t = *b.get_unchecked(0);
+				x += 1;
+			});
+
+			// Chi
+			FOR5!(y, 5, {
+				FOR5!(x, 1, {
+					*b.get_unchecked_mut(x) = *a.get_unchecked(y + x);
+				});
+				FOR5!(x, 1, {
+					*a.get_unchecked_mut(y + x) = *b.get_unchecked(x) ^ ((!b.get_unchecked((x + 1) % 5)) & b.get_unchecked((x + 2) % 5));
+				});
+			});
+
+			// Iota
+			*a.get_unchecked_mut(0) ^= *RC.get_unchecked(i) as u32;
+		}
+	}
+}
+
 fn xorin(dst: &mut [u8], src: &[u8], len: usize) {
 	unsafe {
 		for i in 0..len {
@@ -151,55 +219,83 @@ fn xorin(dst: &mut [u8], src: &[u8], len: usize) {
 
 fn setout(src: &[u8], dst: &mut [u8], len: usize) {
 	unsafe {
-		::std::ptr::copy(src.as_ptr(), dst.as_mut_ptr(), len);
+		core::ptr::copy(src.as_ptr(), dst.as_mut_ptr(), len);
 	}
 }
 
+/// NIST SP 800-185 `left_encode`: `x` as a minimal big-endian byte
+/// string, prefixed by a single byte giving that string's length.
+/// Returns the encoding in a fixed buffer (9 bytes covers any `u64`)
+/// together with its length.
+fn left_encode(x: u64) -> ([u8; 9], usize) {
+	let bytes = x.to_be_bytes();
+	let mut n = 8usize;
+	while n > 1 && bytes[8 - n] == 0 {
+		n -= 1;
+	}
+
+	let mut buf = [0u8; 9];
+	buf[0] = n as u8;
+	buf[1..1 + n].copy_from_slice(&bytes[8 - n..]);
+	(buf, n + 1)
+}
+
+/// NIST SP 800-185 `right_encode`: as `left_encode`, but the length byte
+/// trails the encoded value instead of leading it.
+fn right_encode(x: u64) -> ([u8; 9], usize) {
+	let bytes = x.to_be_bytes();
+	let mut n = 8usize;
+	while n > 1 && bytes[8 - n] == 0 {
+		n -= 1;
+	}
+
+	let mut buf = [0u8; 9];
+	buf[..n].copy_from_slice(&bytes[8 - n..]);
+	buf[n] = n as u8;
+	(buf, n + 1)
+}
+
 /// Total number of lanes.
 const PLEN: usize = 25;
 
 /// Lets cheat borrow checker. 
 fn as_bytes_slice<'a, 'b>(ints: &'a [u64]) -> &'b [u8] {
 	unsafe {
-		::std::slice::from_raw_parts(ints.as_ptr() as *mut u8, ints.len() * 8)
+		core::slice::from_raw_parts(ints.as_ptr() as *mut u8, ints.len() * 8)
 	}
 }
 
 /// Lets cheat borrow checker... again.
 fn as_mut_bytes_slice<'a, 'b>(ints: &'a mut [u64]) -> &'b mut [u8] {
 	unsafe {
-		::std::slice::from_raw_parts_mut(ints.as_mut_ptr() as *mut u8, ints.len() * 8)
+		core::slice::from_raw_parts_mut(ints.as_mut_ptr() as *mut u8, ints.len() * 8)
 	}
 }
 
 /// This structure should be used to create keccak/sha3 hash.
 ///
 /// ```rust
-/// extern crate tiny_keccak;
 /// use tiny_keccak::Keccak;
-/// 
-/// fn main() {
-/// 	let mut sha3 = Keccak::new_sha3_256();
-/// 	let data: Vec<u8> = From::from("hello");
-/// 	let data2: Vec<u8> = From::from("world");
-/// 	
-/// 	sha3.update(&data);
-/// 	sha3.update(&[b' ']);
-/// 	sha3.update(&data2);
 ///
-/// 	let mut res: [u8; 32] = [0; 32];
-/// 	sha3.finalize(&mut res);
+/// let mut sha3 = Keccak::new_sha3_256();
+/// let data: &[u8] = b"hello";
+/// let data2: &[u8] = b"world";
+///
+/// sha3.update(data);
+/// sha3.update(&[b' ']);
+/// sha3.update(data2);
+///
+/// let mut res: [u8; 32] = [0; 32];
+/// sha3.finalize(&mut res);
 ///
-/// 	let expected = vec![
-/// 		0x64, 0x4b, 0xcc, 0x7e, 0x56, 0x43, 0x73, 0x04,
-/// 		0x09, 0x99, 0xaa, 0xc8, 0x9e, 0x76, 0x22, 0xf3,
-/// 		0xca, 0x71, 0xfb, 0xa1, 0xd9, 0x72, 0xfd, 0x94,
-/// 		0xa3, 0x1c, 0x3b, 0xfb, 0xf2, 0x4e, 0x39, 0x38
-/// 	];
+/// let expected: [u8; 32] = [
+/// 	0x64, 0x4b, 0xcc, 0x7e, 0x56, 0x43, 0x73, 0x04,
+/// 	0x09, 0x99, 0xaa, 0xc8, 0x9e, 0x76, 0x22, 0xf3,
+/// 	0xca, 0x71, 0xfb, 0xa1, 0xd9, 0x72, 0xfd, 0x94,
+/// 	0xa3, 0x1c, 0x3b, 0xfb, 0xf2, 0x4e, 0x39, 0x38
+/// ];
 ///
-/// 	let ref_ex: &[u8] = &expected;
-/// 	assert_eq!(&res, ref_ex);
-/// }
+/// assert_eq!(&res, &expected);
 /// ```
 pub struct Keccak {
 	a: [u64; PLEN],
@@ -210,24 +306,25 @@ pub struct Keccak {
 
 impl Clone for Keccak {
 	fn clone(&self) -> Self {
-		use std::mem;
-		use std::ptr;
+		use core::mem;
+		use core::ptr;
 
 		unsafe {
-			let mut res: Keccak = mem::uninitialized();
-			ptr::copy(self.a.as_ptr(), res.a.as_mut_ptr(), self.a.len());
-			res.offset = self.offset;
-			res.rate = self.rate;
-			res.delim = self.delim;
-			res
+			let mut res = mem::MaybeUninit::<Keccak>::uninit();
+			let ptr = res.as_mut_ptr();
+			ptr::copy(self.a.as_ptr(), (*ptr).a.as_mut_ptr(), self.a.len());
+			(*ptr).offset = self.offset;
+			(*ptr).rate = self.rate;
+			(*ptr).delim = self.delim;
+			res.assume_init()
 		}
 	}
 }
 
 macro_rules! impl_constructor {
 	($name: ident, $bits: expr, $delim: expr) => {
-		pub fn $name() -> Keccak {
-			Keccak::new(200 - $bits/4, $delim)
+		pub fn $name() -> Self {
+			Self::new(200 - $bits/4, $delim)
 		}
 	}
 }
@@ -253,13 +350,64 @@ impl Keccak {
 	impl_constructor!(new_sha3_384,  384, 0x06);
 	impl_constructor!(new_sha3_512,  512, 0x06);
 
+	/// NIST SP 800-185 cSHAKE128: plain SHAKE128 when both `name` and
+	/// `custom` are empty, otherwise SHAKE128 primed with the
+	/// `bytepad(encode_string(name) || encode_string(custom), rate)`
+	/// block and switched to the cSHAKE pad byte.
+	pub fn new_cshake128(name: &[u8], custom: &[u8]) -> Keccak {
+		Keccak::new_cshake(128, name, custom)
+	}
+
+	/// See [`new_cshake128`](#method.new_cshake128); the SHAKE256 variant.
+	pub fn new_cshake256(name: &[u8], custom: &[u8]) -> Keccak {
+		Keccak::new_cshake(256, name, custom)
+	}
+
+	fn new_cshake(bits: usize, name: &[u8], custom: &[u8]) -> Keccak {
+		if name.is_empty() && custom.is_empty() {
+			return match bits {
+				128 => Keccak::new_shake128(),
+				_ => Keccak::new_shake256(),
+			};
+		}
+
+		let rate = 200 - bits / 4;
+		let mut keccak = Keccak::new(rate, 0x04);
+		keccak.bytepad_start(rate, &[name, custom]);
+		keccak
+	}
+
+	/// Absorbs `bytepad(encode_string(parts[0]) || encode_string(parts[1]) || ..., w)`,
+	/// the priming block shared by cSHAKE and KMAC.
+	fn bytepad_start(&mut self, w: usize, parts: &[&[u8]]) {
+		let (wbuf, wlen) = left_encode(w as u64);
+		self.update(&wbuf[..wlen]);
+		let mut written = wlen;
+
+		for part in parts {
+			let (lbuf, llen) = left_encode((part.len() as u64) * 8);
+			self.update(&lbuf[..llen]);
+			self.update(part);
+			written += llen + part.len();
+		}
+
+		let pad = (w - (written % w)) % w;
+		let zeros = [0u8; 200];
+		let mut remaining = pad;
+		while remaining > 0 {
+			let chunk = if remaining < zeros.len() { remaining } else { zeros.len() };
+			self.update(&zeros[..chunk]);
+			remaining -= chunk;
+		}
+	}
+
 	pub fn update(&mut self, input: &[u8]) {
 		self.absorb(input);
 	}
 
 	pub fn finalize(mut self, output: &mut [u8]) {
 		self.pad();
-		
+
 		// apply keccakf
 		keccakf(&mut self.a);
 
@@ -267,6 +415,22 @@ impl Keccak {
 		self.squeeze(output);
 	}
 
+	/// Pads and permutes once, then hands back a reader that can be
+	/// squeezed repeatedly to stream an arbitrary amount of output,
+	/// as needed by the SHAKE128/SHAKE256 extendable-output functions.
+	pub fn finalize_xof(mut self) -> XofReader {
+		self.pad();
+
+		// apply keccakf
+		keccakf(&mut self.a);
+
+		XofReader {
+			a: self.a,
+			offset: 0,
+			rate: self.rate
+		}
+	}
+
 	// Absorb input
 	fn absorb(&mut self, input: &[u8]) {
 		let mut a = as_mut_bytes_slice(&mut self.a);
@@ -324,6 +488,397 @@ impl Keccak {
 	}
 }
 
+/// A resumable squeeze over the output of a finalized sponge, used to
+/// stream an extendable-output function (SHAKE128/SHAKE256) in chunks
+/// instead of one fixed-length buffer.
+pub struct XofReader {
+	a: [u64; PLEN],
+	offset: usize,
+	rate: usize
+}
+
+impl XofReader {
+	/// Squeeze out `output.len()` more bytes, continuing from wherever
+	/// the previous call to `squeeze` left off and permuting to refill
+	/// as each `rate`-sized block is exhausted.
+	pub fn squeeze(&mut self, output: &mut [u8]) {
+		let mut op = 0;
+		let mut l = output.len();
+
+		while l > 0 {
+			let available = self.rate - self.offset;
+			let to_copy = if l < available { l } else { available };
+
+			let a = as_bytes_slice(&self.a);
+			setout(&a[self.offset..], &mut output[op..], to_copy);
+
+			self.offset += to_copy;
+			op += to_copy;
+			l -= to_copy;
+
+			if self.offset == self.rate {
+				keccakf(&mut self.a);
+				self.offset = 0;
+			}
+		}
+	}
+}
+
+/// NIST SP 800-185 KMAC: a keyed MAC built on cSHAKE, using `"KMAC"` as
+/// the cSHAKE function name and `custom` as its customization string.
+pub struct Kmac {
+	keccak: Keccak
+}
+
+impl Kmac {
+	/// KMAC128 over `key`, with the given customization string.
+	pub fn new_kmac128(key: &[u8], custom: &[u8]) -> Kmac {
+		Kmac::new(128, key, custom)
+	}
+
+	/// KMAC256 over `key`, with the given customization string.
+	pub fn new_kmac256(key: &[u8], custom: &[u8]) -> Kmac {
+		Kmac::new(256, key, custom)
+	}
+
+	fn new(bits: usize, key: &[u8], custom: &[u8]) -> Kmac {
+		let rate = 200 - bits / 4;
+		let mut keccak = Keccak::new(rate, 0x04);
+		keccak.bytepad_start(rate, &[b"KMAC", custom]);
+		keccak.bytepad_start(rate, &[key]);
+		Kmac { keccak: keccak }
+	}
+
+	pub fn update(&mut self, input: &[u8]) {
+		self.keccak.update(input);
+	}
+
+	pub fn finalize(mut self, output: &mut [u8]) {
+		let (buf, len) = right_encode((output.len() as u64) * 8);
+		self.keccak.update(&buf[..len]);
+		self.keccak.finalize(output);
+	}
+}
+
+/// Vectorized keccak-f[1600] over `LANES` independent states, laid out
+/// so that lane `i` of word `w` of state `i` sits in SIMD element `i` of
+/// `state[w]`. Theta/Rho/Pi/Chi/Iota then become one XOR, one
+/// element-wise `rotate_left`, and one AND-NOT per SIMD register,
+/// applied to all `LANES` states at once.
+///
+/// The NEON path below handles `LANES == 2` and the AVX2 path handles
+/// `LANES == 4`, each as one native vector register; every other `LANES`
+/// (including multiples of the native register width), and every `LANES`
+/// on other targets, falls back to running `keccakf` `LANES` times.
+fn keccakf_xn<const LANES: usize>(state: &mut [[u64; LANES]; PLEN]) {
+	#[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+	{
+		if LANES == 2 {
+			unsafe {
+				let wide: &mut [[u64; 2]; PLEN] = &mut *(state as *mut _ as *mut [[u64; 2]; PLEN]);
+				keccakf_x2_neon(wide);
+			}
+			return;
+		}
+	}
+
+	#[cfg(all(target_arch = "x86_64", target_feature = "avx2"))]
+	{
+		if LANES == 4 {
+			unsafe {
+				let wide: &mut [[u64; 4]; PLEN] = &mut *(state as *mut _ as *mut [[u64; 4]; PLEN]);
+				keccakf_x4_avx2(wide);
+			}
+			return;
+		}
+	}
+
+	keccakf_xn_scalar(state);
+}
+
+/// Portable fallback: just runs the existing scalar `keccakf` once per
+/// lane.
+fn keccakf_xn_scalar<const LANES: usize>(state: &mut [[u64; LANES]; PLEN]) {
+	// `state` is word-major (`[[u64; LANES]; PLEN]`), so a single lane's
+	// words aren't contiguous and there's no iterator over just them.
+	#[allow(clippy::needless_range_loop)]
+	for lane in 0..LANES {
+		let mut a: [u64; PLEN] = [0; PLEN];
+		for w in 0..PLEN {
+			a[w] = state[w][lane];
+		}
+
+		keccakf(&mut a);
+
+		for w in 0..PLEN {
+			state[w][lane] = a[w];
+		}
+	}
+}
+
+#[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+fn keccakf_x2_neon(state: &mut [[u64; 2]; PLEN]) {
+	use core::arch::aarch64::*;
+
+	unsafe {
+		let mut a: [uint64x2_t; PLEN] = [vdupq_n_u64(0); PLEN];
+		for w in 0..PLEN {
+			a[w] = vld1q_u64(state[w].as_ptr());
+		}
+
+		let mut b: [uint64x2_t; 5] = [vdupq_n_u64(0); 5];
+		let mut t: uint64x2_t;
+		let mut x: usize;
+		let mut y: usize;
+
+		for i in 0..24 {
+			// Theta
+			FOR5!(x, 1, {
+				b[x] = vdupq_n_u64(0);
+				FOR5!(y, 5, {
+					b[x] = veorq_u64(b[x], a[x + y]);
+				});
+			});
+
+			FOR5!(x, 1, {
+				let rotated = vorrq_u64(vshlq_n_u64(b[(x + 1) % 5], 1), vshrq_n_u64(b[(x + 1) % 5], 63));
+				FOR5!(y, 5, {
+					a[y + x] = veorq_u64(a[y + x], veorq_u64(b[(x + 4) % 5], rotated));
+				});
+			});
+
+			// Rho and pi
+			t = a[1];
+			x = 0;
+			REPEAT24!({
+				b[0] = a[*PI.get_unchecked(x)];
+				// `RHO` offsets aren't known at compile time, so the
+				// rotation can't use the `vshlq_n_u64`/`vshrq_n_u64`
+				// immediate-shift intrinsics used above for the
+				// constant rotate-by-1; shift by a runtime-built vector
+				// instead (negative counts shift right in `vshlq_u64`).
+				let r = *RHO.get_unchecked(x) as i64;
+				a[*PI.get_unchecked(x)] = vorrq_u64(vshlq_u64(t, vdupq_n_s64(r)), vshlq_u64(t, vdupq_n_s64(r - 64)));
+			}, {
+				t = b[0];
+				x += 1;
+			});
+
+			// Chi
+			FOR5!(y, 5, {
+				FOR5!(x, 1, {
+					b[x] = a[y + x];
+				});
+				FOR5!(x, 1, {
+					a[y + x] = veorq_u64(b[x], vandq_u64(vmvnq_u64(b[(x + 1) % 5]), b[(x + 2) % 5]));
+				});
+			});
+
+			// Iota
+			a[0] = veorq_u64(a[0], vdupq_n_u64(*RC.get_unchecked(i)));
+		}
+
+		for w in 0..PLEN {
+			vst1q_u64(state[w].as_mut_ptr(), a[w]);
+		}
+	}
+}
+
+#[cfg(all(target_arch = "x86_64", target_feature = "avx2"))]
+fn keccakf_x4_avx2(state: &mut [[u64; 4]; PLEN]) {
+	use core::arch::x86_64::*;
+
+	unsafe {
+		let mut a: [__m256i; PLEN] = [_mm256_setzero_si256(); PLEN];
+		for w in 0..PLEN {
+			a[w] = _mm256_loadu_si256(state[w].as_ptr() as *const __m256i);
+		}
+
+		let mut b: [__m256i; 5] = [_mm256_setzero_si256(); 5];
+		let mut t: __m256i;
+		let mut x: usize;
+		let mut y: usize;
+
+		// `RHO` offsets aren't known at compile time, so rotation uses
+		// the variable-shift `sllv`/`srlv` intrinsics (which take the
+		// shift count as a vector operand) rather than the `slli`/`srli`
+		// immediate-shift forms, which require a compile-time constant.
+		let rotl64 = |v: __m256i, r: u32| {
+			_mm256_or_si256(
+				_mm256_sllv_epi64(v, _mm256_set1_epi64x(r as i64)),
+				_mm256_srlv_epi64(v, _mm256_set1_epi64x((64 - r) as i64))
+			)
+		};
+
+		for i in 0..24 {
+			// Theta
+			FOR5!(x, 1, {
+				b[x] = _mm256_setzero_si256();
+				FOR5!(y, 5, {
+					b[x] = _mm256_xor_si256(b[x], a[x + y]);
+				});
+			});
+
+			FOR5!(x, 1, {
+				let rotated = rotl64(b[(x + 1) % 5], 1);
+				FOR5!(y, 5, {
+					a[y + x] = _mm256_xor_si256(a[y + x], _mm256_xor_si256(b[(x + 4) % 5], rotated));
+				});
+			});
+
+			// Rho and pi
+			t = a[1];
+			x = 0;
+			REPEAT24!({
+				b[0] = a[*PI.get_unchecked(x)];
+				a[*PI.get_unchecked(x)] = rotl64(t, *RHO.get_unchecked(x));
+			}, {
+				t = b[0];
+				x += 1;
+			});
+
+			// Chi
+			FOR5!(y, 5, {
+				FOR5!(x, 1, {
+					b[x] = a[y + x];
+				});
+				FOR5!(x, 1, {
+					a[y + x] = _mm256_xor_si256(b[x], _mm256_andnot_si256(b[(x + 1) % 5], b[(x + 2) % 5]));
+				});
+			});
+
+			// Iota
+			a[0] = _mm256_xor_si256(a[0], _mm256_set1_epi64x(*RC.get_unchecked(i) as i64));
+		}
+
+		for w in 0..PLEN {
+			_mm256_storeu_si256(state[w].as_mut_ptr() as *mut __m256i, a[w]);
+		}
+	}
+}
+
+fn xorin_lane<const LANES: usize>(state: &mut [[u64; LANES]; PLEN], lane: usize, offset: usize, src: &[u8], len: usize) {
+	for (i, &byte) in src.iter().enumerate().take(len) {
+		let byte_index = offset + i;
+		let w = byte_index / 8;
+		let b = byte_index % 8;
+		state[w][lane] ^= (byte as u64) << (b * 8);
+	}
+}
+
+fn setout_lane<const LANES: usize>(state: &[[u64; LANES]; PLEN], lane: usize, offset: usize, dst: &mut [u8], len: usize) {
+	for (i, out) in dst.iter_mut().enumerate().take(len) {
+		let byte_index = offset + i;
+		let w = byte_index / 8;
+		let b = byte_index % 8;
+		*out = (state[w][lane] >> (b * 8)) as u8;
+	}
+}
+
+/// Batched/vectorized hashing of `LANES` independent, equal-length
+/// messages with the same parameters at once (Merkle trees, PQ KEMs,
+/// mining candidate enumeration), sharing one `keccakf_xn` call across
+/// all lanes instead of running `Keccak` `LANES` separate times.
+pub struct KeccakN<const LANES: usize> {
+	state: [[u64; LANES]; PLEN],
+	offset: usize,
+	rate: usize,
+	delim: u8
+}
+
+impl<const LANES: usize> KeccakN<LANES> {
+	fn new(rate: usize, delim: u8) -> Self {
+		KeccakN {
+			state: [[0; LANES]; PLEN],
+			offset: 0,
+			rate: rate,
+			delim: delim
+		}
+	}
+
+	impl_constructor!(new_shake128,  128, 0x1f);
+	impl_constructor!(new_shake256,  256, 0x1f);
+	impl_constructor!(new_keccak224, 224, 0x01);
+	impl_constructor!(new_keccak256, 256, 0x01);
+	impl_constructor!(new_keccak384, 384, 0x01);
+	impl_constructor!(new_keccak512, 512, 0x01);
+	impl_constructor!(new_sha3_224,  224, 0x06);
+	impl_constructor!(new_sha3_256,  256, 0x06);
+	impl_constructor!(new_sha3_384,  384, 0x06);
+	impl_constructor!(new_sha3_512,  512, 0x06);
+
+	pub fn update(&mut self, inputs: [&[u8]; LANES]) {
+		self.absorb(inputs);
+	}
+
+	pub fn finalize(mut self, outputs: [&mut [u8]; LANES]) {
+		self.pad();
+
+		keccakf_xn(&mut self.state);
+
+		self.squeeze(outputs);
+	}
+
+	fn absorb(&mut self, inputs: [&[u8]; LANES]) {
+		let inlen = inputs[0].len();
+
+		let mut ip = 0;
+		let mut l = inlen;
+		let mut rate = self.rate - self.offset;
+		while l >= rate {
+			for (lane, input) in inputs.iter().enumerate() {
+				xorin_lane(&mut self.state, lane, self.offset, &input[ip..], rate);
+			}
+			keccakf_xn(&mut self.state);
+			ip += rate;
+			l -= rate;
+			rate = self.rate;
+			self.offset = 0;
+		}
+
+		for (lane, input) in inputs.iter().enumerate() {
+			xorin_lane(&mut self.state, lane, self.offset, &input[ip..], l);
+		}
+		self.offset += l;
+	}
+
+	fn pad(&mut self) {
+		let offset = self.offset;
+		let rate = self.rate;
+
+		for lane in 0..LANES {
+			xorin_lane(&mut self.state, lane, offset, &[self.delim], 1);
+			xorin_lane(&mut self.state, lane, rate - 1, &[0x80], 1);
+		}
+	}
+
+	fn squeeze(&mut self, mut outputs: [&mut [u8]; LANES]) {
+		let rate = self.rate;
+		let outlen = outputs[0].len();
+
+		let mut op = 0;
+		let mut l = outlen;
+		while l >= rate {
+			for (lane, output) in outputs.iter_mut().enumerate() {
+				setout_lane(&self.state, lane, 0, &mut output[op..], rate);
+			}
+			keccakf_xn(&mut self.state);
+			op += rate;
+			l -= rate;
+		}
+
+		for (lane, output) in outputs.iter_mut().enumerate() {
+			setout_lane(&self.state, lane, 0, &mut output[op..], l);
+		}
+	}
+}
+
+/// Batched hashing of 2 independent messages at once.
+pub type Keccak2 = KeccakN<2>;
+
+/// Batched hashing of 4 independent messages at once.
+pub type Keccak4 = KeccakN<4>;
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -334,7 +889,7 @@ mod tests {
 		let mut res: [u8; 32] = [0; 32];
 		keccak.finalize(&mut res);
 
-		let expected = vec![
+		let expected: [u8; 32] = [
 			0xc5, 0xd2, 0x46, 0x01, 0x86, 0xf7, 0x23, 0x3c,
 			0x92, 0x7e, 0x7d, 0xb2, 0xdc, 0xc7, 0x03, 0xc0,
 			0xe5, 0x00, 0xb6, 0x53, 0xca, 0x82, 0x27, 0x3b,
@@ -351,10 +906,10 @@ mod tests {
 		let mut res: [u8; 32] = [0; 32];
 		sha3.finalize(&mut res);
 
-		let expected = vec![
+		let expected: [u8; 32] = [
 			0xa7, 0xff, 0xc6, 0xf8, 0xbf, 0x1e, 0xd7, 0x66,
 			0x51, 0xc1, 0x47, 0x56, 0xa0, 0x61, 0xd6, 0x62,
-			0xf5, 0x80, 0xff, 0x4d, 0xe4, 0x3b, 0x49, 0xfa, 
+			0xf5, 0x80, 0xff, 0x4d, 0xe4, 0x3b, 0x49, 0xfa,
 			0x82, 0xd8, 0x0a, 0x4b, 0x80, 0xf8, 0x43, 0x4a
 		];
 
@@ -365,15 +920,15 @@ mod tests {
 	#[test]
 	fn string_sha3_256() {
 		let mut sha3 = Keccak::new_sha3_256();
-		let data: Vec<u8> = From::from("hello");
-		sha3.update(&data);
+		let data: &[u8] = b"hello";
+		sha3.update(data);
 
 		let mut res: [u8; 32] = [0; 32];
 		sha3.finalize(&mut res);
 
-		let expected = vec![
+		let expected: [u8; 32] = [
 			0x33, 0x38, 0xbe, 0x69, 0x4f, 0x50, 0xc5, 0xf3,
-			0x38, 0x81, 0x49, 0x86, 0xcd, 0xf0, 0x68, 0x64, 
+			0x38, 0x81, 0x49, 0x86, 0xcd, 0xf0, 0x68, 0x64,
 			0x53, 0xa8, 0x88, 0xb8, 0x4f, 0x42, 0x4d, 0x79,
 			0x2a, 0xf4, 0xb9, 0x20, 0x23, 0x98, 0xf3, 0x92
 		];
@@ -385,14 +940,14 @@ mod tests {
 	#[test]
 	fn string_sha3_256_parts() {
 		let mut sha3 = Keccak::new_sha3_256();
-		let data: Vec<u8> = From::from("hell");
-		sha3.update(&data);
+		let data: &[u8] = b"hell";
+		sha3.update(data);
 		sha3.update(&[b'o']);
 
 		let mut res: [u8; 32] = [0; 32];
 		sha3.finalize(&mut res);
 
-		let expected = vec![
+		let expected: [u8; 32] = [
 			0x33, 0x38, 0xbe, 0x69, 0x4f, 0x50, 0xc5, 0xf3,
 			0x38, 0x81, 0x49, 0x86, 0xcd, 0xf0, 0x68, 0x64, 
 			0x53, 0xa8, 0x88, 0xb8, 0x4f, 0x42, 0x4d, 0x79,
@@ -415,9 +970,9 @@ mod tests {
 		let mut res: [u8; 32] = [0; 32];
 		sha3.finalize(&mut res);
 
-		let expected = vec![
+		let expected: [u8; 32] = [
 			0x33, 0x38, 0xbe, 0x69, 0x4f, 0x50, 0xc5, 0xf3,
-			0x38, 0x81, 0x49, 0x86, 0xcd, 0xf0, 0x68, 0x64, 
+			0x38, 0x81, 0x49, 0x86, 0xcd, 0xf0, 0x68, 0x64,
 			0x53, 0xa8, 0x88, 0xb8, 0x4f, 0x42, 0x4d, 0x79,
 			0x2a, 0xf4, 0xb9, 0x20, 0x23, 0x98, 0xf3, 0x92
 		];
@@ -429,13 +984,13 @@ mod tests {
 	#[test]
 	fn long_string_sha3_512() {
 		let mut sha3 = Keccak::new_sha3_512();
-		let data: Vec<u8> = From::from("Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor incididunt ut labore et dolore magna aliqua. Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris nisi ut aliquip ex ea commodo consequat. Duis aute irure dolor in reprehenderit in voluptate velit esse cillum dolore eu fugiat nulla pariatur. Excepteur sint occaecat cupidatat non proident, sunt in culpa qui officia deserunt mollit anim id est laborum.");
+		let data: &[u8] = b"Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor incididunt ut labore et dolore magna aliqua. Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris nisi ut aliquip ex ea commodo consequat. Duis aute irure dolor in reprehenderit in voluptate velit esse cillum dolore eu fugiat nulla pariatur. Excepteur sint occaecat cupidatat non proident, sunt in culpa qui officia deserunt mollit anim id est laborum.";
 
-		sha3.update(&data);
+		sha3.update(data);
 		let mut res: [u8; 64] = [0; 64];
 		sha3.finalize(&mut res);
 
-		let expected = vec![
+		let expected: [u8; 64] = [
 			0xf3, 0x2a, 0x94, 0x23, 0x55, 0x13, 0x51, 0xdf, 
 			0x0a, 0x07, 0xc0, 0xb8, 0xc2, 0x0e, 0xb9, 0x72,
 			0x36, 0x7c, 0x39, 0x8d, 0x61, 0x06, 0x60, 0x38,
@@ -455,16 +1010,16 @@ mod tests {
 	#[test]
 	fn long_string_sha3_512_parts() {
 		let mut sha3 = Keccak::new_sha3_512();
-		let data: Vec<u8> = From::from("Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor incididunt ut labore et dolore magna aliqua. Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris nisi ut aliquip ");
-		let data2: Vec<u8> = From::from("ex ea commodo consequat. Duis aute irure dolor in reprehenderit in voluptate velit esse cillum dolore eu fugiat nulla pariatur. Excepteur sint occaecat cupidatat non proident, sunt in culpa qui officia deserunt mollit anim id est laborum.");
+		let data: &[u8] = b"Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor incididunt ut labore et dolore magna aliqua. Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris nisi ut aliquip ";
+		let data2: &[u8] = b"ex ea commodo consequat. Duis aute irure dolor in reprehenderit in voluptate velit esse cillum dolore eu fugiat nulla pariatur. Excepteur sint occaecat cupidatat non proident, sunt in culpa qui officia deserunt mollit anim id est laborum.";
 
-		sha3.update(&data);
-		sha3.update(&data2);
+		sha3.update(data);
+		sha3.update(data2);
 
 		let mut res: [u8; 64] = [0; 64];
 		sha3.finalize(&mut res);
 
-		let expected = vec![
+		let expected: [u8; 64] = [
 			0xf3, 0x2a, 0x94, 0x23, 0x55, 0x13, 0x51, 0xdf, 
 			0x0a, 0x07, 0xc0, 0xb8, 0xc2, 0x0e, 0xb9, 0x72,
 			0x36, 0x7c, 0x39, 0x8d, 0x61, 0x06, 0x60, 0x38,
@@ -479,5 +1034,216 @@ mod tests {
 		let ref_ex: &[u8] = &expected;
 		assert_eq!(ref_res, ref_ex);
 	}
+
+	#[test]
+	fn left_and_right_encode_small_values() {
+		let (buf, len) = left_encode(0);
+		assert_eq!(&buf[..len], &[0x01, 0x00]);
+
+		let (buf, len) = left_encode(128);
+		assert_eq!(&buf[..len], &[0x01, 0x80]);
+
+		let (buf, len) = right_encode(0);
+		assert_eq!(&buf[..len], &[0x00, 0x01]);
+
+		let (buf, len) = right_encode(128);
+		assert_eq!(&buf[..len], &[0x80, 0x01]);
+	}
+
+	#[test]
+	fn cshake128_matches_nist_sample() {
+		let data: [u8; 4] = [0x00, 0x01, 0x02, 0x03];
+		let mut cshake = Keccak::new_cshake128(b"", b"Email Signature");
+		cshake.update(&data);
+
+		let mut res: [u8; 32] = [0; 32];
+		cshake.finalize(&mut res);
+
+		let expected = [
+			0xc1, 0xc3, 0x69, 0x25, 0xb6, 0x40, 0x9a, 0x04,
+			0xf1, 0xb5, 0x04, 0xfc, 0xbc, 0xa9, 0xd8, 0x2b,
+			0x40, 0x17, 0x27, 0x7c, 0xb5, 0xed, 0x2b, 0x20,
+			0x65, 0xfc, 0x1d, 0x38, 0x14, 0xd5, 0xaa, 0xf5
+		];
+
+		assert_eq!(&res[..], &expected[..]);
+	}
+
+	#[test]
+	fn cshake128_empty_name_and_custom_matches_shake128() {
+		let mut cshake = Keccak::new_cshake128(b"", b"");
+		cshake.update(b"hello");
+
+		let mut shake = Keccak::new_shake128();
+		shake.update(b"hello");
+
+		let mut res: [u8; 32] = [0; 32];
+		let mut expected: [u8; 32] = [0; 32];
+		cshake.finalize(&mut res);
+		shake.finalize(&mut expected);
+
+		assert_eq!(&res[..], &expected[..]);
+	}
+
+	#[test]
+	fn kmac128_is_deterministic_and_key_dependent() {
+		let mut kmac_a = Kmac::new_kmac128(b"my secret key", b"");
+		kmac_a.update(b"hello world");
+		let mut res_a: [u8; 32] = [0; 32];
+		kmac_a.finalize(&mut res_a);
+
+		let mut kmac_b = Kmac::new_kmac128(b"my secret key", b"");
+		kmac_b.update(b"hello world");
+		let mut res_b: [u8; 32] = [0; 32];
+		kmac_b.finalize(&mut res_b);
+
+		let mut kmac_c = Kmac::new_kmac128(b"a different key", b"");
+		kmac_c.update(b"hello world");
+		let mut res_c: [u8; 32] = [0; 32];
+		kmac_c.finalize(&mut res_c);
+
+		assert_eq!(&res_a[..], &res_b[..]);
+		assert_ne!(&res_a[..], &res_c[..]);
+	}
+
+	#[test]
+	fn keccak2_matches_two_scalar_keccaks() {
+		let mut k2: Keccak2 = KeccakN::new_sha3_256();
+		k2.update([b"hello", b"world"]);
+
+		let mut res0: [u8; 32] = [0; 32];
+		let mut res1: [u8; 32] = [0; 32];
+		k2.finalize([&mut res0, &mut res1]);
+
+		let mut expected0: [u8; 32] = [0; 32];
+		let mut sha3_0 = Keccak::new_sha3_256();
+		sha3_0.update(b"hello");
+		sha3_0.finalize(&mut expected0);
+
+		let mut expected1: [u8; 32] = [0; 32];
+		let mut sha3_1 = Keccak::new_sha3_256();
+		sha3_1.update(b"world");
+		sha3_1.finalize(&mut expected1);
+
+		assert_eq!(&res0[..], &expected0[..]);
+		assert_eq!(&res1[..], &expected1[..]);
+	}
+
+	#[test]
+	// This only reaches `keccakf_x4_avx2` when the crate is built with AVX2
+	// enabled, e.g. `RUSTFLAGS="-C target-feature=+avx2" cargo test`;
+	// otherwise `LANES == 4` dispatches to the scalar fallback, which this
+	// test still checks.
+	fn keccak4_matches_four_scalar_keccaks() {
+		let mut k4: Keccak4 = KeccakN::new_sha3_256();
+		k4.update([b"apple", b"mango", b"grape", b"lemon"]);
+
+		let mut res0: [u8; 32] = [0; 32];
+		let mut res1: [u8; 32] = [0; 32];
+		let mut res2: [u8; 32] = [0; 32];
+		let mut res3: [u8; 32] = [0; 32];
+		k4.finalize([&mut res0, &mut res1, &mut res2, &mut res3]);
+
+		let mut expected0: [u8; 32] = [0; 32];
+		let mut sha3_0 = Keccak::new_sha3_256();
+		sha3_0.update(b"apple");
+		sha3_0.finalize(&mut expected0);
+
+		let mut expected1: [u8; 32] = [0; 32];
+		let mut sha3_1 = Keccak::new_sha3_256();
+		sha3_1.update(b"mango");
+		sha3_1.finalize(&mut expected1);
+
+		let mut expected2: [u8; 32] = [0; 32];
+		let mut sha3_2 = Keccak::new_sha3_256();
+		sha3_2.update(b"grape");
+		sha3_2.finalize(&mut expected2);
+
+		let mut expected3: [u8; 32] = [0; 32];
+		let mut sha3_3 = Keccak::new_sha3_256();
+		sha3_3.update(b"lemon");
+		sha3_3.finalize(&mut expected3);
+
+		assert_eq!(&res0[..], &expected0[..]);
+		assert_eq!(&res1[..], &expected1[..]);
+		assert_eq!(&res2[..], &expected2[..]);
+		assert_eq!(&res3[..], &expected3[..]);
+	}
+
+	#[test]
+	fn keccak_p_24_rounds_matches_keccakf() {
+		let mut a: [u64; PLEN] = [0; PLEN];
+		let mut b: [u64; PLEN] = [0; PLEN];
+		for i in 0..PLEN {
+			a[i] = i as u64;
+			b[i] = i as u64;
+		}
+
+		keccakf(&mut a);
+		keccak_p(&mut b, 24);
+
+		assert_eq!(&a[..], &b[..]);
+	}
+
+	#[test]
+	fn keccak_f800_zero_state_matches_known_vector() {
+		let mut a: [u32; 25] = [0; 25];
+		keccak_f800(&mut a, 22);
+
+		let expected: [u32; 25] = [
+			0xe531d45d, 0xf404c6fb, 0x23a0bf99, 0xf1f8452f, 0x51ffd042,
+			0xe539f578, 0xf00b80a7, 0xaf973664, 0xbf5af34c, 0x227a2424,
+			0x88172715, 0x9f685884, 0xb15cd054, 0x1bf4fc0e, 0x6166fa91,
+			0x1a9e599a, 0xa3970a1f, 0xab659687, 0xafab8d68, 0xe74b1015,
+			0x34001a98, 0x4119eff3, 0x930a0e76, 0x87b28070, 0x11efe996
+		];
+
+		assert_eq!(a, expected);
+	}
+
+	#[test]
+	fn shake128_xof_matches_finalize() {
+		let mut shake = Keccak::new_shake128();
+		let data: &[u8] = b"hello";
+		shake.update(data);
+
+		let mut expected: [u8; 64] = [0; 64];
+		shake.clone().finalize(&mut expected);
+
+		let mut streamed: [u8; 64] = [0; 64];
+		let mut xof = shake.finalize_xof();
+		xof.squeeze(&mut streamed[0..17]);
+		xof.squeeze(&mut streamed[17..64]);
+
+		let ref_streamed: &[u8] = &streamed;
+		let ref_expected: &[u8] = &expected;
+		assert_eq!(ref_streamed, ref_expected);
+	}
+
+	#[test]
+	fn shake128_xof_matches_finalize_across_rate_boundary() {
+		// SHAKE128's rate is 168 bytes; squeeze well past that in unevenly
+		// sized chunks so the `keccakf` refill branch in `XofReader::squeeze`
+		// actually gets exercised.
+		let mut shake = Keccak::new_shake128();
+		let data: &[u8] = b"hello world, this is a longer message";
+		shake.update(data);
+
+		let mut expected: [u8; 400] = [0; 400];
+		shake.clone().finalize(&mut expected);
+
+		let mut streamed: [u8; 400] = [0; 400];
+		let mut xof = shake.finalize_xof();
+		xof.squeeze(&mut streamed[0..17]);
+		xof.squeeze(&mut streamed[17..64]);
+		xof.squeeze(&mut streamed[64..168]);
+		xof.squeeze(&mut streamed[168..169]);
+		xof.squeeze(&mut streamed[169..336]);
+		xof.squeeze(&mut streamed[336..400]);
+
+		let ref_streamed: &[u8] = &streamed;
+		let ref_expected: &[u8] = &expected;
+		assert_eq!(ref_streamed, ref_expected);
+	}
 }
 